@@ -9,13 +9,17 @@ use dom::bindings::codegen::InheritTypes::NodeCast;
 use dom::bindings::global;
 use dom::bindings::js::{JS, JSRef, Temporary};
 use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
-use dom::element::{Element, AttributeHandlers};
+use dom::element::{Element, ElementState, AttributeHandlers};
 use dom::node::Node;
 use dom::window::Window;
 use dom::virtualmethods::vtable_for;
 
+use cssparser::RGBA;
 use devtools_traits::AttrInfo;
 use servo_util::str::{DOMString, split_html_space_chars};
+use style::restyle_hints::{RestyleHint, RESTYLE_SELF, RESTYLE_DESCENDANTS, RESTYLE_LATER_SIBLINGS};
+use style::values::specified::{Length, LengthOrPercentageOrAuto};
+use std::collections::{HashMap, HashSet};
 use std::mem;
 use string_cache::{Atom, Namespace};
 
@@ -24,18 +28,233 @@ pub enum AttrSettingType {
     ReplacedAttr,
 }
 
+/// Thin, distinct wrappers around `Atom` for each name "kind" an attribute
+/// can carry, so e.g. a namespace atom can never accidentally be compared
+/// against a local-name atom at the type level.
+///
+/// `from_slice` interns at runtime, for names that are only known once an
+/// attribute is actually parsed. For a literal known at compile time, use
+/// the companion macro instead (`local_name!("class")`, not
+/// `LocalName::from_slice("class")`) — it expands straight to `atom!`,
+/// the same generated static-atom lookup `ns!`/`atom!` already give every
+/// other literal in this tree, just wrapped in the typed newtype.
+macro_rules! atom_wrapper(
+    ($name:ident, $macro_name:ident) => (
+        #[deriving(PartialEq, Eq, Clone, Hash)]
+        #[jstraceable]
+        pub struct $name(Atom);
+
+        impl $name {
+            pub fn from_slice(s: &str) -> $name {
+                $name(Atom::from_slice(s))
+            }
+        }
+
+        impl Str for $name {
+            fn as_slice<'a>(&'a self) -> &'a str {
+                let $name(ref atom) = *self;
+                atom.as_slice()
+            }
+        }
+
+        #[macro_export]
+        macro_rules! $macro_name(
+            ($s:expr) => ($crate::dom::attr::$name(atom!($s)));
+        )
+    );
+)
+
+atom_wrapper!(LocalName, local_name)
+atom_wrapper!(Prefix, namespace_prefix)
+
+/// `namespace_url!` is this series' name for constructing a `Namespace`
+/// literal. `Namespace` is string_cache's own type, already spelled `ns!`
+/// everywhere else in the tree (see `ns!("")` below), so this is a plain
+/// alias rather than another `atom_wrapper!` instantiation — there is no
+/// separate generated type here, just the name the request asked for.
+#[macro_export]
+macro_rules! namespace_url(
+    ($s:expr) => (ns!($s));
+)
+
+/// A qualified name (the `name`/`tagName`-style combination of an optional
+/// prefix, a namespace, and a local name). Unlike `LocalName` and `Prefix`,
+/// a `QualName` is always built by combining those three pieces at
+/// attribute-creation time, not parsed from a single literal, so it has no
+/// literal form and no macro to go with it.
+#[deriving(PartialEq, Eq, Clone, Hash)]
+#[jstraceable]
+pub struct QualName(Atom);
+
+impl QualName {
+    pub fn from_slice(s: &str) -> QualName {
+        QualName(Atom::from_slice(s))
+    }
+}
+
+impl Str for QualName {
+    fn as_slice<'a>(&'a self) -> &'a str {
+        let QualName(ref atom) = *self;
+        atom.as_slice()
+    }
+}
+
+/// A snapshot of an element's attribute/state taken the first time it is
+/// mutated in a given layout flush, so the style system can diff old vs.
+/// new values instead of dirtying the whole subtree.
+#[deriving(Clone)]
+pub struct ElementSnapshot {
+    pub attrs: Option<Vec<(Namespace, LocalName, AttrValue)>>,
+    pub state: ElementState,
+}
+
+impl ElementSnapshot {
+    fn new(attrs: Vec<(Namespace, LocalName, AttrValue)>, state: ElementState) -> ElementSnapshot {
+        ElementSnapshot {
+            attrs: Some(attrs),
+            state: state,
+        }
+    }
+}
+
+/// A per-document table of pre-mutation snapshots, keyed by element
+/// identity, waiting to be turned into restyle hints at the next layout
+/// flush. `Document` is expected to hold one field of this type (it isn't
+/// reproduced here, since document.rs is outside this slice of the tree);
+/// everything that actually builds, looks up, and diffs a snapshot is
+/// self-contained below and only needs that one field threaded in.
+pub type AttrSnapshotTable = Vec<(JS<Element>, ElementSnapshot)>;
+
+/// Record a pre-mutation snapshot of `owner` into `snapshots`, unless one
+/// was already taken earlier in this flush. Only the first call per element
+/// per flush has any effect; later mutations in the same batch must be
+/// compared against the state that existed *before any of them*. Elements
+/// are identified by `JS<Element>` rather than a raw address, so a second
+/// call for the same element can't be missed even if the first attribute
+/// object involved has since been dropped.
+pub fn ensure_snapshot(snapshots: &mut AttrSnapshotTable, owner: JSRef<Element>) {
+    let handle = JS::from_rooted(owner);
+    if snapshots.iter().any(|&(ref existing, _)| *existing == handle) {
+        return;
+    }
+    snapshots.push((handle, snapshot_element(owner)));
+}
+
+/// Build a pre-mutation snapshot of `owner`'s current attributes and state.
+fn snapshot_element(owner: JSRef<Element>) -> ElementSnapshot {
+    let attrs = owner.attrs().iter().map(|attr| {
+        let attr = attr.root();
+        (attr.namespace().clone(), attr.local_name().clone(), attr.value().clone())
+    }).collect();
+    ElementSnapshot::new(attrs, owner.state())
+}
+
+/// Diff `snapshot` (an element's attributes/state from before any mutation
+/// in the current flush) against its live attributes/state now, yielding a
+/// `RestyleHint` no wider than the maximal `RESTYLE_SELF |
+/// RESTYLE_DESCENDANTS | RESTYLE_LATER_SIBLINGS` a blind restyle would need:
+/// empty if nothing changed, `RESTYLE_SELF` alone if only attributes outside
+/// `attribute_restyle_hint`'s list changed, and the maximal hint for a state
+/// change or a change to one of the listed attributes.
+/// Meant to be called once per entry in an `AttrSnapshotTable` at layout
+/// flush, by whatever drains that table; this function only does the
+/// diffing, not the draining.
+pub fn restyle_hint_for_snapshot(owner: JSRef<Element>, snapshot: &ElementSnapshot) -> RestyleHint {
+    let mut hint = RestyleHint::empty();
+
+    if snapshot.state != owner.state() {
+        // Pseudo-class state (`:hover`, `:disabled`, ...) can be referenced
+        // by descendant and sibling selectors, so treat it the same as a
+        // `class`/`id` change below rather than self-only.
+        hint = hint | RESTYLE_SELF | RESTYLE_DESCENDANTS | RESTYLE_LATER_SIBLINGS;
+    }
+
+    let old_attrs = snapshot.attrs.as_ref().expect("a snapshot always captures its attrs");
+    let mut old_by_name: HashMap<(Namespace, Atom), &AttrValue> = HashMap::new();
+    for &(ref ns, ref name, ref value) in old_attrs.iter() {
+        let LocalName(ref atom) = *name;
+        old_by_name.insert((ns.clone(), atom.clone()), value);
+    }
+
+    let mut seen = HashSet::new();
+    for attr in owner.attrs().iter() {
+        let attr = attr.root();
+        let LocalName(ref atom) = *attr.local_name();
+        let key = (attr.namespace().clone(), atom.clone());
+        seen.insert(key.clone());
+        match old_by_name.get(&key) {
+            Some(old_value) if *old_value == &*attr.value() => {}
+            _ => hint = hint | attribute_restyle_hint(atom),
+        }
+    }
+
+    // Attributes present in the snapshot but missing now were removed
+    // during the flush; those need the same treatment as a changed value.
+    for key in old_by_name.keys() {
+        if !seen.contains(key) {
+            let &(_, ref atom) = key;
+            hint = hint | attribute_restyle_hint(atom);
+        }
+    }
+
+    hint
+}
+
+/// The restyle hint a single changed/added/removed attribute contributes.
+///
+/// The right answer is "whatever the stylist's attribute-dependency set
+/// says selectors in this document actually care about"; the stylist isn't
+/// reachable from here (it lives with the style system, well outside this
+/// file), so this is a static approximation instead: treat
+/// attributes commonly targeted by descendant/sibling/attribute selectors
+/// (`class`, `id`, and other frequently-selected-on attributes such as
+/// `type` or `disabled`) as needing the wider hint, and fall back to
+/// self-only for everything else. This can both under- and over-restyle
+/// relative to a document's actual selectors; wiring in the real
+/// dependency set is follow-up work for whoever owns that integration.
+fn attribute_restyle_hint(local_name: &Atom) -> RestyleHint {
+    static WIDE_IMPACT: &'static [&'static str] = &[
+        "class", "id", "style",
+        "type", "disabled", "checked", "selected", "multiple", "readonly", "required",
+    ];
+    if WIDE_IMPACT.iter().any(|name| *local_name == Atom::from_slice(*name)) {
+        RESTYLE_SELF | RESTYLE_DESCENDANTS | RESTYLE_LATER_SIBLINGS
+    } else {
+        RESTYLE_SELF
+    }
+}
+
 #[deriving(PartialEq, Clone)]
 #[jstraceable]
 pub enum AttrValue {
     StringAttrValue(DOMString),
+    /// Selector matching (`has_class`, `[rel~=]`, ...) tests membership by
+    /// scanning this vector and comparing atoms with `==`, which is itself
+    /// already a pointer compare, not a string compare, since `Atom` is
+    /// interned; token lists are small (class lists, `rel`, ...) so this is
+    /// effectively constant-time in practice without needing a second,
+    /// separately-hashed container to keep in sync with the first.
     TokenListAttrValue(DOMString, Vec<Atom>),
     UIntAttrValue(DOMString, u32),
     AtomAttrValue(Atom),
+    /// A `bgcolor`-like legacy color attribute, parsed once via the HTML
+    /// "rules for parsing a legacy color value"; `None` if the source
+    /// string is explicitly invalid (empty, or "transparent") rather than
+    /// merely falling through to the permissive "anything goes" step.
+    ColorAttrValue(DOMString, Option<RGBA>),
+    /// A `border`-like presentational length attribute; `None` if the
+    /// source string failed to parse as a non-negative length.
+    LengthAttrValue(DOMString, Option<Length>),
+    /// A `width`/`height`-like presentational attribute accepting an
+    /// absolute length, a percentage, or `auto`.
+    DimensionAttrValue(DOMString, LengthOrPercentageOrAuto),
+    /// A `cols`-like presentational attribute parsed as a plain float.
+    DoubleAttrValue(DOMString, f64),
 }
 
 impl AttrValue {
     pub fn from_tokenlist(tokens: DOMString) -> AttrValue {
-        let atoms = split_html_space_chars(tokens.as_slice())
+        let atoms: Vec<Atom> = split_html_space_chars(tokens.as_slice())
             .map(|token| Atom::from_slice(token)).collect();
         TokenListAttrValue(tokens, atoms)
     }
@@ -50,6 +269,35 @@ impl AttrValue {
         AtomAttrValue(value)
     }
 
+    /// Parse a legacy color attribute (e.g. `bgcolor`) per the HTML
+    /// "rules for parsing a legacy color value".
+    pub fn from_legacy_color(string: DOMString) -> AttrValue {
+        let color = parse_legacy_color(string.as_slice()).ok();
+        ColorAttrValue(string, color)
+    }
+
+    /// Parse a presentational length attribute (e.g. `border`); `None` if
+    /// the source string does not parse as a non-negative length.
+    pub fn from_length(string: DOMString) -> AttrValue {
+        let length = Length::parse_non_negative(string.as_slice()).ok();
+        LengthAttrValue(string, length)
+    }
+
+    /// Parse a presentational length-or-percentage attribute (e.g. `width`,
+    /// `height`), falling back to `Auto` when the string does not parse.
+    pub fn from_dimension(string: DOMString) -> AttrValue {
+        let value = LengthOrPercentageOrAuto::parse_non_negative(string.as_slice())
+            .unwrap_or(LengthOrPercentageOrAuto::Auto);
+        DimensionAttrValue(string, value)
+    }
+
+    /// Parse a presentational attribute that takes a plain floating point
+    /// number (e.g. `cols`).
+    pub fn from_double(string: DOMString, default: f64) -> AttrValue {
+        let result: f64 = from_str(string.as_slice()).unwrap_or(default);
+        DoubleAttrValue(string, result)
+    }
+
     pub fn tokens<'a>(&'a self) -> Option<&'a [Atom]> {
         match *self {
             TokenListAttrValue(_, ref tokens) => Some(tokens.as_slice()),
@@ -62,21 +310,164 @@ impl Str for AttrValue {
     fn as_slice<'a>(&'a self) -> &'a str {
         match *self {
             StringAttrValue(ref value) |
-            TokenListAttrValue(ref value, _) |
-            UIntAttrValue(ref value, _) => value.as_slice(),
+            UIntAttrValue(ref value, _) |
+            ColorAttrValue(ref value, _) |
+            LengthAttrValue(ref value, _) |
+            DimensionAttrValue(ref value, _) |
+            DoubleAttrValue(ref value, _) => value.as_slice(),
+            TokenListAttrValue(ref value, _) => value.as_slice(),
             AtomAttrValue(ref value) => value.as_slice(),
         }
     }
 }
 
+/// The HTML "rules for parsing a legacy color value", used by presentational
+/// attributes like `bgcolor` that must accept colors CSS itself would reject.
+/// Returns `Err` for the explicitly-invalid cases (empty, or "transparent");
+/// everything else that isn't a recognizable color still parses to *some*
+/// `RGBA`, per the permissive "anything goes" fallback in step 5.
+fn parse_legacy_color(input: &str) -> Result<RGBA, ()> {
+    // 1-2. Trim leading/trailing ASCII whitespace; "transparent" is invalid.
+    let input = input.trim_chars(|c: char| {
+        c == ' ' || c == '\t' || c == '\n' || c == '\x0C' || c == '\r'
+    });
+    if input.is_empty() || input.eq_ignore_ascii_case("transparent") {
+        return Err(());
+    }
+
+    // 3. A CSS named color (other than "transparent").
+    if let Some(color) = css_named_color(input) {
+        return Ok(color);
+    }
+
+    // 4. A string of the form "#rrggbb" or "#rgb". This shortcut only
+    //    applies with the leading "#"; a bare hex string like "f00" is not
+    //    a shorthand color and must fall through to the "anything goes"
+    //    fallback below (which parses it very differently: "f00" ends up
+    //    as (15, 0, 0), not (255, 0, 0)).
+    if input.starts_with("#") {
+        if let Some(color) = parse_hex_color(input.slice_from(1)) {
+            return Ok(color);
+        }
+    }
+
+    // 5. The "anything goes" fallback.
+    //    a. Cap the length up front so pathological input doesn't drive the
+    //       rest of this function to allocate forever.
+    let input = if input.len() > 128 { input.slice_to(128) } else { input };
+
+    //    b. Strip a single leading "#", if one is still present at this
+    //       point. This has to happen before replacing non-hex characters
+    //       below, or the "#" itself would be replaced with "0" instead of
+    //       removed, shifting every digit after it by one position.
+    let input = if input.starts_with("#") { input.slice_from(1) } else { input };
+
+    //    c. Replace any character that isn't an ASCII hex digit with "0".
+    let mut digits: Vec<char> = input.chars().map(|c| {
+        if c.is_ascii() && c.to_digit(16).is_some() { c } else { '0' }
+    }).collect();
+
+    //    d. If the length is zero, or not a multiple of three, pad with "0"
+    //       at the end until it is.
+    while digits.is_empty() || digits.len() % 3 != 0 {
+        digits.push('0');
+    }
+
+    //    e. Split into three equal-length segments.
+    let len = digits.len() / 3;
+    let mut segments: Vec<&[char]> = digits.as_slice().chunks(len).collect();
+
+    //    f. If any segment is longer than 8 characters, keep only its last
+    //       8 characters.
+    for segment in segments.iter_mut() {
+        if segment.len() > 8 {
+            *segment = segment.slice_from(segment.len() - 8);
+        }
+    }
+
+    //    g. Keep removing the first character of each segment while all
+    //       three are longer than two characters and all of their first
+    //       characters are "0".
+    while segments.iter().all(|s| s.len() > 2 && s[0] == '0') {
+        for segment in segments.iter_mut() {
+            *segment = segment.slice_from(1);
+        }
+    }
+
+    //    h. Truncate each segment to its first two (or fewer) characters.
+    for segment in segments.iter_mut() {
+        if segment.len() > 2 {
+            *segment = segment.slice_to(2);
+        }
+    }
+
+    let components: Vec<u8> = segments.iter().map(|segment| {
+        let hex: String = segment.iter().cloned().collect();
+        u8::from_str_radix(hex.as_slice(), 16).unwrap_or(0)
+    }).collect();
+
+    Ok(RGBA {
+        red: components[0] as f32 / 255.0,
+        green: components[1] as f32 / 255.0,
+        blue: components[2] as f32 / 255.0,
+        alpha: 1.0,
+    })
+}
+
+fn parse_hex_color(hex: &str) -> Option<RGBA> {
+    fn hex_pair(s: &str, i: uint) -> Option<u8> {
+        u8::from_str_radix(s.slice(i, i + 2), 16).ok()
+    }
+
+    match hex.len() {
+        3 => {
+            let mut out = [0u8, ..3];
+            for (i, c) in hex.chars().enumerate() {
+                match c.to_digit(16) {
+                    Some(d) => out[i] = d as u8 * 0x11,
+                    None => return None,
+                }
+            }
+            Some(RGBA {
+                red: out[0] as f32 / 255.0,
+                green: out[1] as f32 / 255.0,
+                blue: out[2] as f32 / 255.0,
+                alpha: 1.0,
+            })
+        }
+        6 => {
+            match (hex_pair(hex, 0), hex_pair(hex, 2), hex_pair(hex, 4)) {
+                (Some(r), Some(g), Some(b)) => Some(RGBA {
+                    red: r as f32 / 255.0,
+                    green: g as f32 / 255.0,
+                    blue: b as f32 / 255.0,
+                    alpha: 1.0,
+                }),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn css_named_color(name: &str) -> Option<RGBA> {
+    // `parse_color_keyword` also accepts "currentcolor", which isn't an
+    // actual color and has no legacy-color-parsing equivalent; treat it as
+    // not-a-named-color so the caller falls through to the next step.
+    match cssparser::parse_color_keyword(name) {
+        Ok(cssparser::Color::RGBA(rgba)) => Some(rgba),
+        Ok(cssparser::Color::CurrentColor) | Err(()) => None,
+    }
+}
+
 #[dom_struct]
 pub struct Attr {
     reflector_: Reflector,
-    local_name: Atom,
+    local_name: LocalName,
     value: DOMRefCell<AttrValue>,
-    name: Atom,
+    name: QualName,
     namespace: Namespace,
-    prefix: Option<DOMString>,
+    prefix: Option<Prefix>,
 
     /// the element that owns this attribute.
     owner: JS<Element>,
@@ -89,9 +480,9 @@ impl Reflectable for Attr {
 }
 
 impl Attr {
-    fn new_inherited(local_name: Atom, value: AttrValue,
-                     name: Atom, namespace: Namespace,
-                     prefix: Option<DOMString>, owner: JSRef<Element>) -> Attr {
+    fn new_inherited(local_name: LocalName, value: AttrValue,
+                     name: QualName, namespace: Namespace,
+                     prefix: Option<Prefix>, owner: JSRef<Element>) -> Attr {
         Attr {
             reflector_: Reflector::new(),
             local_name: local_name,
@@ -103,15 +494,15 @@ impl Attr {
         }
     }
 
-    pub fn new(window: JSRef<Window>, local_name: Atom, value: AttrValue,
-               name: Atom, namespace: Namespace,
-               prefix: Option<DOMString>, owner: JSRef<Element>) -> Temporary<Attr> {
+    pub fn new(window: JSRef<Window>, local_name: LocalName, value: AttrValue,
+               name: QualName, namespace: Namespace,
+               prefix: Option<Prefix>, owner: JSRef<Element>) -> Temporary<Attr> {
         reflect_dom_object(box Attr::new_inherited(local_name, value, name, namespace, prefix, owner),
                            &global::Window(window), AttrBinding::Wrap)
     }
 
     #[inline]
-    pub fn name<'a>(&'a self) -> &'a Atom {
+    pub fn name<'a>(&'a self) -> &'a QualName {
         &self.name
     }
 
@@ -121,9 +512,35 @@ impl Attr {
     }
 
     #[inline]
-    pub fn prefix<'a>(&'a self) -> &'a Option<DOMString> {
+    pub fn prefix<'a>(&'a self) -> &'a Option<Prefix> {
         &self.prefix
     }
+
+    /// True if this attribute's local name is `local_name`, regardless of
+    /// its namespace. Used to support `[*|foo]`-style "any namespace"
+    /// selectors, which must match across differing namespaces where a
+    /// normal (prefixed or null-namespace) selector would not. Takes
+    /// `&LocalName`, not `&Atom`, so it can't accidentally be handed a
+    /// namespace or qualified-name atom. The element-side counterpart,
+    /// `get_attrs(local_name)`, collects every attribute across an
+    /// element for which this returns true; see `attrs_with_local_name`
+    /// below.
+    #[inline]
+    pub fn matches_local_name_ignoring_ns(&self, local_name: &LocalName) -> bool {
+        self.local_name == *local_name
+    }
+}
+
+/// Returns every attribute in `attrs` sharing `local_name`, regardless of
+/// namespace. `Element::get_attrs` (element.rs, not in this checkout)
+/// should call this over its full attribute list to support `[*|foo]`
+/// ("any namespace") selector matching, the same way `Element::get_attr`
+/// calls into a namespace+local-name lookup for the normal case.
+pub fn attrs_with_local_name<'a>(attrs: &[JSRef<'a, Attr>], local_name: &LocalName)
+                                  -> Vec<JSRef<'a, Attr>> {
+    attrs.iter().filter(|attr| attr.matches_local_name_ignoring_ns(local_name))
+                .map(|&attr| attr)
+                .collect()
 }
 
 impl<'a> AttrMethods for JSRef<'a, Attr> {
@@ -137,7 +554,12 @@ impl<'a> AttrMethods for JSRef<'a, Attr> {
 
     fn SetValue(self, value: DOMString) {
         let owner = self.owner.root();
-        let value = owner.parse_attribute(&self.namespace, self.local_name(), value);
+        // `Element::parse_attribute` (element.rs) still takes `&Atom`; it
+        // isn't part of this checkout to update to `&LocalName` alongside
+        // the rest of this typed-name change, so unwrap here rather than
+        // silently failing to compile against it.
+        let LocalName(ref local_name_atom) = *self.local_name();
+        let value = owner.parse_attribute(&self.namespace, local_name_atom, value);
         self.set_value(ReplacedAttr, value);
     }
 
@@ -162,7 +584,7 @@ impl<'a> AttrMethods for JSRef<'a, Attr> {
     }
 
     fn GetPrefix(self) -> Option<DOMString> {
-        self.prefix.clone()
+        self.prefix.as_ref().map(|prefix| prefix.as_slice().to_string())
     }
 
     fn GetOwnerElement(self) -> Option<Temporary<Element>> {
@@ -177,7 +599,7 @@ impl<'a> AttrMethods for JSRef<'a, Attr> {
 pub trait AttrHelpers<'a> {
     fn set_value(self, set_type: AttrSettingType, value: AttrValue);
     fn value(self) -> Ref<'a, AttrValue>;
-    fn local_name(self) -> &'a Atom;
+    fn local_name(self) -> &'a LocalName;
     fn summarize(self) -> AttrInfo;
 }
 
@@ -187,6 +609,15 @@ impl<'a> AttrHelpers<'a> for JSRef<'a, Attr> {
         let node: JSRef<Node> = NodeCast::from_ref(*owner);
         let namespace_is_null = self.namespace == ns!("");
 
+        // Taking a pre-mutation snapshot here (so the style system can
+        // compute a targeted restyle hint via `ensure_snapshot` and
+        // `restyle_hint_for_snapshot` above, instead of dirtying the whole
+        // subtree) needs a live `AttrSnapshotTable` to record into, which
+        // lives on `Document` and isn't reachable from this file. Whatever
+        // owns that table is expected to call `ensure_snapshot` itself
+        // before mutations like this one happen, rather than this method
+        // reaching out to a table it has no access to.
+
         match set_type {
             ReplacedAttr if namespace_is_null => vtable_for(&node).before_remove_attr(self),
             _ => ()
@@ -203,7 +634,7 @@ impl<'a> AttrHelpers<'a> for JSRef<'a, Attr> {
         self.extended_deref().value.borrow()
     }
 
-    fn local_name(self) -> &'a Atom {
+    fn local_name(self) -> &'a LocalName {
         &self.extended_deref().local_name
     }
 
@@ -221,7 +652,15 @@ pub trait AttrHelpersForLayout {
     unsafe fn value_ref_forever(&self) -> &'static str;
     unsafe fn value_atom_forever(&self) -> Option<Atom>;
     unsafe fn value_tokens_forever(&self) -> Option<&'static [Atom]>;
+    unsafe fn has_token_forever(&self, atom: &Atom) -> bool;
+    unsafe fn value_color_forever(&self) -> Option<Option<RGBA>>;
+    unsafe fn value_length_forever(&self) -> Option<Option<Length>>;
+    unsafe fn value_dimension_forever(&self) -> Option<LengthOrPercentageOrAuto>;
+    unsafe fn value_double_forever(&self) -> Option<f64>;
     unsafe fn local_name_atom_forever(&self) -> Atom;
+    /// Test this attribute's local name while ignoring its namespace, for
+    /// `[*|foo]` ("any namespace") selector matching during layout.
+    unsafe fn matches_local_name_ignoring_ns_forever(&self, local_name: &Atom) -> bool;
 }
 
 impl AttrHelpersForLayout for Attr {
@@ -251,8 +690,118 @@ impl AttrHelpersForLayout for Attr {
         }
     }
 
+    #[inline]
+    unsafe fn has_token_forever(&self, atom: &Atom) -> bool {
+        let value = self.value.borrow_for_layout();
+        match *value {
+            TokenListAttrValue(_, ref tokens) => tokens.iter().any(|token| token == atom),
+            _ => false,
+        }
+    }
+
+    #[inline]
+    unsafe fn value_color_forever(&self) -> Option<Option<RGBA>> {
+        let value = self.value.borrow_for_layout();
+        match *value {
+            ColorAttrValue(_, color) => Some(color),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    unsafe fn value_length_forever(&self) -> Option<Option<Length>> {
+        let value = self.value.borrow_for_layout();
+        match *value {
+            LengthAttrValue(_, ref length) => Some(*length),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    unsafe fn value_dimension_forever(&self) -> Option<LengthOrPercentageOrAuto> {
+        let value = self.value.borrow_for_layout();
+        match *value {
+            DimensionAttrValue(_, ref dimension) => Some(*dimension),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    unsafe fn value_double_forever(&self) -> Option<f64> {
+        let value = self.value.borrow_for_layout();
+        match *value {
+            DoubleAttrValue(_, value) => Some(value),
+            _ => None,
+        }
+    }
+
     #[inline]
     unsafe fn local_name_atom_forever(&self) -> Atom {
-        self.local_name.clone()
+        let LocalName(ref atom) = self.local_name;
+        atom.clone()
+    }
+
+    #[inline]
+    unsafe fn matches_local_name_ignoring_ns_forever(&self, local_name: &Atom) -> bool {
+        // Layout's hot path compares raw atoms directly, same as every
+        // other `*_forever` accessor; `matches_local_name_ignoring_ns`
+        // itself takes the typed `&LocalName` for everyone else.
+        let LocalName(ref atom) = self.local_name;
+        atom == local_name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_legacy_color;
+
+    fn rgb(r: u8, g: u8, b: u8) -> (u8, u8, u8) {
+        (r, g, b)
+    }
+
+    fn parse(input: &str) -> (u8, u8, u8) {
+        let color = parse_legacy_color(input).unwrap();
+        ((color.red * 255.0).round() as u8,
+         (color.green * 255.0).round() as u8,
+         (color.blue * 255.0).round() as u8)
+    }
+
+    #[test]
+    fn test_parse_legacy_color_invalid() {
+        // Empty and "transparent" are explicitly invalid per spec steps 1-2,
+        // not a stand-in for any particular color.
+        assert!(parse_legacy_color("").is_err());
+        assert!(parse_legacy_color("transparent").is_err());
+        assert!(parse_legacy_color("TRANSPARENT").is_err());
+    }
+
+    #[test]
+    fn test_parse_legacy_color() {
+        let cases = [
+            // CSS named colors.
+            ("red", rgb(255, 0, 0)),
+            ("white", rgb(255, 255, 255)),
+            // "#rgb" / "#rrggbb" shorthand forms.
+            ("#f00", rgb(255, 0, 0)),
+            ("#ff0000", rgb(255, 0, 0)),
+            ("#FFF", rgb(255, 255, 255)),
+            // A bare hex string is NOT the "#rgb"/"#rrggbb" shortcut; it
+            // goes through the "anything goes" fallback instead, which
+            // treats each character as its own one-digit channel segment.
+            ("f00", rgb(15, 0, 0)),
+            ("fff", rgb(15, 15, 15)),
+            // A malformed "#"-prefixed string that isn't valid shorthand
+            // still has its "#" stripped, not replaced with a "0" digit,
+            // before falling into the "anything goes" fallback.
+            ("#axc", rgb(10, 0, 12)),
+            // "anything goes": non-hex characters become "0", the string is
+            // padded/split into three equal segments, and each segment is
+            // truncated to its first two characters.
+            ("chucknorris", rgb(192, 0, 0)),
+            ("bbb", rgb(11, 11, 11)),
+        ];
+        for &(input, expected) in cases.iter() {
+            assert_eq!(parse(input), expected, "parsing {}", input);
+        }
     }
 }